@@ -1,26 +1,493 @@
 // src/main.rs
 use clap::Parser;
 use serde::Serialize;
-use std::net::ToSocketAddrs;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::AsyncReadExt;
-use tokio::net::TcpStream;
+use tokio::net::{TcpStream, UdpSocket};
 use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tokio::time::timeout;
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_rustls::TlsConnector;
 
-fn estimate_timeout(ip: &str, max_timeout: Duration) -> Duration {
+/// Small built-in table of well-known service names accepted in a `--ports` spec,
+/// so users can write `http,https,ssh` instead of looking up port numbers.
+fn lookup_service(name: &str) -> Option<u16> {
+    let port = match name.to_ascii_lowercase().as_str() {
+        "ftp" => 21,
+        "ssh" => 22,
+        "telnet" => 23,
+        "smtp" => 25,
+        "dns" => 53,
+        "http" => 80,
+        "pop3" => 110,
+        "imap" => 143,
+        "https" => 443,
+        "smb" => 445,
+        "imaps" => 993,
+        "pop3s" => 995,
+        "mysql" => 3306,
+        "rdp" => 3389,
+        "postgres" => 5432,
+        _ => return None,
+    };
+    Some(port)
+}
+
+/// Parse a `--ports`/`-p` spec into a deduplicated, sorted list of ports.
+///
+/// Accepts comma-separated entries, each of which is a single port (`80`),
+/// a range (`1-1024`), or a named service resolved via `lookup_service`
+/// (`http`, `https`, `ssh`, ...). Reversed ranges and values outside the u16
+/// range are rejected with a descriptive error.
+fn parse_ports(spec: &str) -> Result<Vec<u16>, String> {
+    let mut ports = std::collections::BTreeSet::new();
+
+    for raw in spec.split(',') {
+        let entry = raw.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        if let Some((start, end)) = entry.split_once('-') {
+            let start = start.trim();
+            let end = end.trim();
+            let start: u16 = start
+                .parse()
+                .map_err(|_| format!("invalid port in range '{}': '{}'", entry, start))?;
+            let end: u16 = end
+                .parse()
+                .map_err(|_| format!("invalid port in range '{}': '{}'", entry, end))?;
+            if start > end {
+                return Err(format!(
+                    "reversed port range '{}': start {} is greater than end {}",
+                    entry, start, end
+                ));
+            }
+            ports.extend(start..=end);
+        } else if let Ok(port) = entry.parse::<u16>() {
+            ports.insert(port);
+        } else if entry.parse::<u64>().is_ok() {
+            return Err(format!("port out of range (0-65535): '{}'", entry));
+        } else if let Some(port) = lookup_service(entry) {
+            ports.insert(port);
+        } else {
+            return Err(format!("unrecognized port or service name: '{}'", entry));
+        }
+    }
+
+    if ports.is_empty() {
+        return Err("port spec resolved to an empty set".to_string());
+    }
+
+    Ok(ports.into_iter().collect())
+}
+
+/// Ports in `all_ports` with no entry in `completed`, in the same order as
+/// `all_ports`. Used to mark ports whose scan task never finished (aborted
+/// past an absolute `--max-duration` deadline) as `"unknown"` instead of
+/// silently dropping them from the report.
+fn missing_ports(all_ports: &[u16], completed: &std::collections::HashSet<u16>) -> Vec<u16> {
+    all_ports.iter().copied().filter(|p| !completed.contains(p)).collect()
+}
+
+/// Largest number of hosts a single CIDR block may expand to. Subnet sweeps
+/// are the intended use case, but an unguarded `/8` (or `/0`) would silently
+/// try to materialize millions of addresses before a single connection is
+/// attempted; real scanners refuse that rather than hanging, so we do too.
+const MAX_CIDR_HOSTS: u32 = 4096;
+
+/// Expand a CIDR block (e.g. `10.0.0.0/24`) into its usable host addresses.
+///
+/// For prefixes shorter than 31, the network and broadcast addresses are
+/// excluded, matching how subnet sweeps are normally scoped; `/31` and `/32`
+/// blocks are returned in full since they have no distinct broadcast address.
+/// Blocks that would expand past `MAX_CIDR_HOSTS` are rejected outright.
+fn expand_cidr(spec: &str) -> Result<Vec<String>, String> {
+    let (addr, prefix) = spec
+        .split_once('/')
+        .ok_or_else(|| format!("not a CIDR block: '{}'", spec))?;
+    let addr: std::net::Ipv4Addr = addr
+        .parse()
+        .map_err(|_| format!("invalid CIDR address: '{}'", addr))?;
+    let prefix: u32 = prefix
+        .parse()
+        .map_err(|_| format!("invalid CIDR prefix: '{}'", prefix))?;
+    if prefix > 32 {
+        return Err(format!("invalid CIDR prefix: /{}", prefix));
+    }
+
+    let base = u32::from(addr);
+    let host_bits = 32 - prefix;
+    let mask = if host_bits == 32 { 0 } else { !0u32 << host_bits };
+    let network = base & mask;
+    let broadcast = network | !mask;
+
+    let (first, last) = if prefix >= 31 {
+        (network, broadcast)
+    } else {
+        (network + 1, broadcast - 1)
+    };
+    let host_count = last - first + 1;
+    if host_count > MAX_CIDR_HOSTS {
+        return Err(format!(
+            "CIDR block '{}' expands to {} hosts, which exceeds the {} host limit; use a narrower prefix",
+            spec, host_count, MAX_CIDR_HOSTS
+        ));
+    }
+
+    Ok((first..=last)
+        .map(|n| std::net::Ipv4Addr::from(n).to_string())
+        .collect())
+}
+
+/// Expand a dashed IPv4 range (e.g. `192.168.1.1-50`) into its host addresses.
+///
+/// Only the last octet may be a range; the first three octets are shared by
+/// every address in the expansion.
+fn expand_dashed_range(spec: &str) -> Result<Vec<String>, String> {
+    let mut octets = spec.rsplitn(2, '.');
+    let last = octets.next().ok_or_else(|| format!("invalid range: '{}'", spec))?;
+    let prefix = octets.next().ok_or_else(|| format!("invalid range: '{}'", spec))?;
+
+    let (start, end) = last
+        .split_once('-')
+        .ok_or_else(|| format!("invalid range: '{}'", spec))?;
+    let start: u8 = start
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid range start in '{}': '{}'", spec, start))?;
+    let end: u8 = end
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid range end in '{}': '{}'", spec, end))?;
+    if start > end {
+        return Err(format!(
+            "reversed host range '{}': start {} is greater than end {}",
+            spec, start, end
+        ));
+    }
+
+    Ok((start..=end).map(|o| format!("{}.{}", prefix, o)).collect())
+}
+
+/// Parse `--host` into a concrete, deduplicated list of targets (IPs or DNS
+/// names), expanding comma-separated entries, CIDR blocks (`10.0.0.0/24`),
+/// and dashed IPv4 ranges (`192.168.1.1-50`) along the way. Plain IPs and
+/// hostnames pass through unchanged for resolution later.
+fn parse_targets(spec: &str) -> Result<Vec<String>, String> {
+    let mut targets = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+
+    for raw in spec.split(',') {
+        let entry = raw.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let expanded = if entry.contains('/') {
+            expand_cidr(entry)?
+        } else if entry
+            .rsplit('.')
+            .next()
+            .is_some_and(|o| o.split_once('-').is_some_and(|(s, e)| {
+                !s.trim().is_empty() && s.trim().bytes().all(|b| b.is_ascii_digit())
+                    && !e.trim().is_empty() && e.trim().bytes().all(|b| b.is_ascii_digit())
+            }))
+        {
+            expand_dashed_range(entry)?
+        } else {
+            vec![entry.to_string()]
+        };
+
+        for t in expanded {
+            if seen.insert(t.clone()) {
+                targets.push(t);
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        return Err("host spec resolved to an empty set".to_string());
+    }
+
+    Ok(targets)
+}
+
+/// Interleave resolved addresses per RFC 8305, alternating families starting
+/// with IPv6 (v6, v4, v6, v4, ...) so neither family is starved when racing
+/// connections below.
+fn interleave_rfc8305(addrs: &[IpAddr]) -> Vec<IpAddr> {
+    let v6: Vec<IpAddr> = addrs.iter().copied().filter(|a| a.is_ipv6()).collect();
+    let v4: Vec<IpAddr> = addrs.iter().copied().filter(|a| a.is_ipv4()).collect();
+    let mut out = Vec::with_capacity(addrs.len());
+    let (mut i, mut j) = (0, 0);
+    loop {
+        let mut pushed = false;
+        if i < v6.len() {
+            out.push(v6[i]);
+            i += 1;
+            pushed = true;
+        }
+        if j < v4.len() {
+            out.push(v4[j]);
+            j += 1;
+            pushed = true;
+        }
+        if !pushed {
+            break;
+        }
+    }
+    out
+}
+
+/// The stagger between successive Happy Eyeballs connection attempts (RFC 8305 ~250ms).
+const HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+/// Resolve a host spec and pick its best-reachable address using a Happy
+/// Eyeballs (RFC 8305) race: addresses are interleaved v6/v4 and dialed with
+/// a staggered delay between attempts on a `probe_port` control connection,
+/// keeping whichever connects first and cancelling the rest. Falls back to
+/// the first candidate address if nothing answers, so the scan can still
+/// proceed against a host with no open control port.
+async fn resolve_happy_eyeballs(host: &str, probe_port: u16, timeout_dur: Duration) -> Result<IpAddr, String> {
+    let addrs: Vec<IpAddr> = (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("resolve error for {}: {}", host, e))?
+        .map(|s| s.ip())
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("failed to resolve host: {}", host));
+    }
+
+    let sorted = interleave_rfc8305(&addrs);
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    let mut handles = Vec::with_capacity(sorted.len());
+    for (idx, ip) in sorted.iter().enumerate() {
+        let ip = *ip;
+        let tx = tx.clone();
+        let delay = HAPPY_EYEBALLS_DELAY * idx as u32;
+        handles.push(tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let addr = SocketAddr::new(ip, probe_port);
+            if let Ok(Ok(_)) = timeout(timeout_dur, TcpStream::connect(addr)).await {
+                let _ = tx.send(ip).await;
+            }
+        }));
+    }
+    drop(tx);
+
+    let winner = rx.recv().await;
+    for h in &handles {
+        h.abort();
+    }
+
+    Ok(winner.unwrap_or(sorted[0]))
+}
+
+/// Classify a failed TCP connect attempt's error kind into a port status.
+///
+/// A refused connection is a definitive "closed"; anything else (e.g. host
+/// unreachable) is as uninformative as a timeout, so it's reported "filtered".
+fn classify_connect_error(kind: std::io::ErrorKind) -> &'static str {
+    if kind == std::io::ErrorKind::ConnectionRefused {
+        "closed"
+    } else {
+        "filtered"
+    }
+}
+
+/// Classify a UDP recv outcome into a port status.
+///
+/// A datagram back means the port is definitely `open`. A connected UDP
+/// socket surfaces an ICMP port-unreachable as a `ConnectionRefused` error on
+/// the next send/recv, which we report as `closed`. A timeout with no reply
+/// at all is the common case for a silently dropped or genuinely listening
+/// port, so it's reported as `open|filtered`, matching nmap's convention.
+fn classify_udp_recv(result: Result<Result<usize, std::io::ErrorKind>, tokio::time::error::Elapsed>) -> &'static str {
+    match result {
+        Ok(Ok(_)) => "open",
+        Ok(Err(std::io::ErrorKind::ConnectionRefused)) => "closed",
+        Ok(Err(_)) => "open|filtered",
+        Err(_) => "open|filtered",
+    }
+}
+
+/// Probe a single UDP port: send an empty datagram and classify the reply.
+async fn probe_udp(addr: SocketAddr, timeout_dur: Duration) -> &'static str {
+    let sock = match UdpSocket::bind("0.0.0.0:0").await {
+        Ok(s) => s,
+        Err(_) => return "closed",
+    };
+    if sock.connect(addr).await.is_err() {
+        return "closed";
+    }
+    if sock.send(&[]).await.is_err() {
+        return "closed";
+    }
+
+    let mut buf = [0u8; 512];
+    let result = timeout(timeout_dur, sock.recv(&mut buf))
+        .await
+        .map(|r| r.map_err(|e| e.kind()));
+    classify_udp_recv(result)
+}
+
+/// Ports that get a TLS handshake attempt even without `--tls`, since they're
+/// conventionally encrypted services.
+const AUTO_TLS_PORTS: [u16; 5] = [443, 465, 993, 995, 8443];
+
+/// Certificate and handshake details captured from a `--tls` probe.
+#[derive(Serialize, Clone)]
+struct TlsInfo {
+    version: String,
+    alpn: Option<String>,
+    subject: String,
+    issuer: String,
+    san: Vec<String>,
+    not_before: String,
+    not_after: String,
+}
+
+/// Accepts any certificate chain: we're fingerprinting services, not
+/// authenticating them, so expired or mismatched certs should surface in
+/// results rather than abort the handshake.
+#[derive(Debug)]
+struct AcceptAnyCert;
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        // Accept whatever the peer offers; we never actually validate the signature.
+        vec![
+            SignatureScheme::RSA_PKCS1_SHA1,
+            SignatureScheme::ECDSA_SHA1_Legacy,
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::ECDSA_NISTP521_SHA512,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::ED25519,
+        ]
+    }
+}
+
+/// Format a negotiated TLS protocol version, falling back to `"unknown"` when
+/// the connection state doesn't expose one (shouldn't happen post-handshake,
+/// but rustls models it as optional).
+fn format_protocol_version(version: Option<tokio_rustls::rustls::ProtocolVersion>) -> String {
+    version
+        .map(|v| format!("{:?}", v))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Format a negotiated ALPN protocol identifier as a lossily-decoded string.
+fn format_alpn(alpn: Option<&[u8]>) -> Option<String> {
+    alpn.map(|p| String::from_utf8_lossy(p).to_string())
+}
+
+/// Perform a TLS handshake over an already-connected TCP stream and extract
+/// the negotiated protocol/ALPN plus the leaf certificate's identity fields.
+/// Returns `None` on any handshake or certificate-parsing failure, since a
+/// failed TLS probe is just treated as "no TLS info" rather than an error.
+async fn probe_tls(stream: TcpStream, sni: &str, timeout_dur: Duration) -> Option<TlsInfo> {
+    let config = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+        .with_no_client_auth();
+    let connector = TlsConnector::from(Arc::new(config));
+    let server_name = ServerName::try_from(sni.to_string()).ok()?;
+
+    let tls_stream = timeout(timeout_dur, connector.connect(server_name, stream))
+        .await
+        .ok()?
+        .ok()?;
+
+    let (_, conn) = tls_stream.get_ref();
+    let version = format_protocol_version(conn.protocol_version());
+    let alpn = format_alpn(conn.alpn_protocol());
+
+    let cert = conn.peer_certificates()?.first()?;
+    let (_, x509) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+    let san = x509
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|ext| ext.value.general_names.iter().map(|n| n.to_string()).collect())
+        .unwrap_or_default();
+
+    Some(TlsInfo {
+        version,
+        alpn,
+        subject: x509.subject().to_string(),
+        issuer: x509.issuer().to_string(),
+        san,
+        not_before: x509.validity().not_before.to_string(),
+        not_after: x509.validity().not_after.to_string(),
+    })
+}
+
+/// `budget` caps the total time this function may spend probing: each sample's
+/// blocking connect is capped to `budget / samples.len()`, and probing stops
+/// early once `budget` is exhausted, so this can't blow past an absolute
+/// `--max-duration` deadline the way an unconditional 5x200ms probe would.
+fn estimate_timeout(ip: IpAddr, max_timeout: Duration, budget: Duration) -> Duration {
     let samples = [22u16, 80u16, 443u16, 53u16, 25u16];
+    let per_sample_cap = std::cmp::min(Duration::from_millis(200), budget / samples.len() as u32);
+    let probe_deadline = std::time::Instant::now() + budget;
     let mut durations = Vec::with_capacity(samples.len());
-    for sp in samples { 
-        let addr = format!("{}:{}", ip, sp);
+    for sp in samples {
+        if std::time::Instant::now() >= probe_deadline {
+            break;
+        }
+        let addr = SocketAddr::new(ip, sp);
         let start = std::time::Instant::now();
         // Use blocking connect in a tiny tokio::task::block_in_place? Simpler: best-effort async with small timeout
         // This function runs before we spawn many tasks, so a small block is fine.
-        let _ = std::net::TcpStream::connect_timeout(&addr.parse().ok().unwrap_or_else(|| std::net::SocketAddr::from(([127,0,0,1], sp))), std::time::Duration::from_millis(200));
+        let _ = std::net::TcpStream::connect_timeout(&addr, per_sample_cap);
         let d = start.elapsed();
         durations.push(d);
     }
+    if durations.is_empty() {
+        return max_timeout;
+    }
     durations.sort();
     let median = durations[durations.len()/2];
     let floor = Duration::from_millis(150);
@@ -33,15 +500,13 @@ fn estimate_timeout(ip: &str, max_timeout: Duration) -> Duration {
 #[derive(Parser, Debug)]
 #[command(author, version, about = "High-performance concurrent port scanner (Rust + Tokio)")]
 struct Args {
-    /// target host (ip or domain)
+    /// target host(s): comma-separated IPs/domains, CIDR blocks (10.0.0.0/24), and dashed ranges (192.168.1.1-50)
     #[arg(long)]
     host: String,
 
-    #[arg(long, default_value_t = 1)]
-    start: u16,
-
-    #[arg(long, default_value_t = 1024)]
-    end: u16,
+    /// port spec: single ports, ranges, and named services (e.g. "1-1024,443,http,https")
+    #[arg(short = 'p', long = "ports", default_value = "1-1024")]
+    ports: String,
 
     #[arg(long, default_value_t = 500)]
     workers: usize,
@@ -64,6 +529,23 @@ struct Args {
     /// optimize settings for public internet targets
     #[arg(long, default_value_t = false)]
     fast_public: bool,
+
+    /// probe protocol: "tcp" (connect scan) or "udp" (send/recv with open|filtered classification)
+    #[arg(long, default_value = "tcp")]
+    protocol: String,
+
+    /// shorthand for --protocol udp
+    #[arg(long, default_value_t = false)]
+    udp: bool,
+
+    /// perform a TLS handshake and report certificate info instead of a plaintext banner
+    /// (always attempted on conventionally-encrypted ports: 443, 465, 993, 995, 8443)
+    #[arg(long, default_value_t = false)]
+    tls: bool,
+
+    /// cap total wall-clock scan time in milliseconds; unfinished ports are reported as "unknown"
+    #[arg(long)]
+    max_duration: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -71,124 +553,358 @@ struct ResultRec {
     port: u16,
     status: &'static str,
     banner: Option<String>,
+    tls: Option<TlsInfo>,
+}
+
+/// An open port's detail: its banner (plaintext services) or TLS handshake
+/// info (encrypted services probed with `--tls`, or on an `AUTO_TLS_PORTS` port).
+#[derive(Serialize)]
+struct OpenPort {
+    port: u16,
+    banner: Option<String>,
+    tls: Option<TlsInfo>,
+}
+
+/// Grouped JSON output for a single target: the host label as given (or
+/// expanded from a CIDR/range), its open ports, and any ports a firewall
+/// appears to be dropping rather than actively refusing.
+#[derive(Serialize)]
+struct HostResult {
+    host: String,
+    family: String,
+    open_ports: Vec<OpenPort>,
+    filtered_ports: Vec<u16>,
+    unknown_ports: Vec<u16>,
 }
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
+    // rustls needs a process-wide crypto provider installed before any ClientConfig is built.
+    let _ = tokio_rustls::rustls::crypto::ring::default_provider().install_default();
+
     let args = Args::parse();
     let started = std::time::Instant::now();
 
-    if args.end < args.start {
-        eprintln!("end must be >= start");
+    let ports = match parse_ports(&args.ports) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("invalid --ports spec: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let protocol: &'static str = if args.udp || args.protocol == "udp" {
+        "udp"
+    } else if args.protocol == "tcp" {
+        "tcp"
+    } else {
+        eprintln!("invalid --protocol '{}': expected 'tcp' or 'udp'", args.protocol);
         std::process::exit(2);
-    }
+    };
 
-    // Resolve host to first IP: use ToSocketAddrs
-    let ip = match (args.host.as_str(), 0).to_socket_addrs() {
-        Ok(mut it) => match it.next() {
-            Some(s) => s.ip().to_string(),
-            None => {
-                eprintln!("failed to resolve host");
-                std::process::exit(1);
-            }
-        },
+    let target_specs = match parse_targets(&args.host) {
+        Ok(t) => t,
         Err(e) => {
-            eprintln!("resolve error: {}", e);
-            std::process::exit(1);
+            eprintln!("invalid --host spec: {}", e);
+            std::process::exit(2);
         }
     };
 
-    // Derive dial timeout: optionally adaptive based on quick probes
-    let base_timeout = Duration::from_millis(args.timeout);
-    let mut timeout_dur = if args.adaptive && !args.fast_public { estimate_timeout(&ip, base_timeout) } else { base_timeout };
+    // --workers also governs resolution concurrency below, and --fast-public's
+    // worker/retry bump applies regardless of resolve outcome, so pin both down
+    // before touching the network.
     let mut workers = args.workers;
     let mut retries = args.retries;
     if args.fast_public {
-        if timeout_dur > Duration::from_millis(80) { timeout_dur = Duration::from_millis(80); }
         if retries > 0 { retries = 0; }
         if workers < 2000 { workers = 2000; }
     }
 
-    // Wrap semaphore in Arc so it can be cheaply cloned between tasks
-    let sem = Arc::new(Semaphore::new(workers));
-    let mut handles = Vec::new();
-
-    for port in args.start..=args.end {
-        let sem_clone = Arc::clone(&sem);
-        let ip_cloned = ip.clone();
-        let timeout_dur = timeout_dur.clone();
+    // Absolute deadline (Go-style SetDeadline) bounding total scan wall-clock time.
+    // Computed up front so it covers resolution below too, not just the port-probe
+    // phase that used to be the only thing it bounded.
+    let deadline = args.max_duration.map(|ms| started + Duration::from_millis(ms));
 
-        let handle = tokio::spawn(async move {
-            // Acquire an owned permit; it releases automatically when dropped.
+    // Resolve every target concurrently via a Happy Eyeballs race so a subnet sweep
+    // doesn't pay each host's resolve timeout sequentially before scanning even starts;
+    // scan tasks never touch DNS and dual-stack hosts pick whichever family answers.
+    // Gated through a --workers-sized semaphore, the same concurrency governor the
+    // scan phase uses below, so a large CIDR sweep doesn't fire thousands of raw
+    // sockets at once. Bounded by `deadline` with the same select!-against-a-sleep
+    // pattern the scan drain loop below uses: once the deadline passes, outstanding
+    // resolves are aborted and whatever resolved in time is used, rather than letting
+    // resolution alone blow through the requested --max-duration budget.
+    let probe_port = ports[0];
+    let resolve_timeout = Duration::from_millis(args.timeout);
+    let resolve_sem = Arc::new(Semaphore::new(workers));
+    let mut resolves = JoinSet::new();
+    for (idx, spec) in target_specs.iter().enumerate() {
+        let spec = spec.clone();
+        let sem_clone = Arc::clone(&resolve_sem);
+        resolves.spawn(async move {
             let _permit = sem_clone.acquire_owned().await.unwrap();
-            let addr = format!("{}:{}", ip_cloned, port);
-
-            // attempt connect with timeout + retries on timeout
-            let attempts = retries + 1;
-            for try_idx in 0..attempts {
-                match timeout(timeout_dur, TcpStream::connect(&addr)).await {
-                    Ok(Ok(mut stream)) => {
-                        let mut buf = [0u8; 256];
-                        let banner = match timeout(Duration::from_millis(50), stream.read(&mut buf)).await {
-                            Ok(Ok(n)) if n > 0 => Some(String::from_utf8_lossy(&buf[..n]).to_string()),
-                            _ => None,
-                        };
-                        return ResultRec { port, status: "open", banner };
+            let result = resolve_happy_eyeballs(&spec, probe_port, resolve_timeout).await;
+            (idx, spec, result)
+        });
+    }
+    let mut resolved: Vec<Option<(String, IpAddr, &'static str)>> = (0..target_specs.len()).map(|_| None).collect();
+    loop {
+        let Some(dl) = deadline else {
+            match resolves.join_next().await {
+                Some(Ok((idx, spec, Ok(ip)))) => {
+                    let family = if ip.is_ipv6() { "IPv6" } else { "IPv4" };
+                    resolved[idx] = Some((spec, ip, family));
+                }
+                Some(Ok((_, _spec, Err(e)))) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+                Some(Err(_)) => {}
+                None => break,
+            }
+            continue;
+        };
+
+        let remaining = dl.saturating_duration_since(std::time::Instant::now());
+        tokio::select! {
+            res = resolves.join_next() => {
+                match res {
+                    Some(Ok((idx, spec, Ok(ip)))) => {
+                        let family = if ip.is_ipv6() { "IPv6" } else { "IPv4" };
+                        resolved[idx] = Some((spec, ip, family));
+                    }
+                    Some(Ok((_, _spec, Err(e)))) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
                     }
-                    Ok(Err(_)) => break,        // immediate refused -> closed
-                    Err(_) if try_idx + 1 < attempts => continue, // timeout -> retry
-                    Err(_) => break,
+                    Some(Err(_)) => {}
+                    None => break,
                 }
             }
-            ResultRec { port, status: "closed", banner: None }
-        });
+            _ = tokio::time::sleep(remaining) => {
+                resolves.abort_all();
+                break;
+            }
+        }
+    }
+    let targets: Vec<(String, IpAddr, &'static str)> = resolved.into_iter().flatten().collect();
 
-        handles.push(handle);
+    if targets.is_empty() {
+        println!(
+            "No targets resolved before --max-duration elapsed (0 of {} requested)",
+            target_specs.len()
+        );
+        return;
     }
 
-    let mut results = Vec::with_capacity((args.end - args.start + 1) as usize);
-    for h in handles {
-        if let Ok(r) = h.await {
-            results.push(r);
+    // Derive dial timeout: optionally adaptive based on quick probes against the first
+    // target, capped to whatever's left of the deadline so the probe itself can't blow it.
+    let base_timeout = Duration::from_millis(args.timeout);
+    let mut timeout_dur = if args.adaptive && !args.fast_public {
+        let probe_budget = deadline
+            .map(|dl| dl.saturating_duration_since(std::time::Instant::now()))
+            .unwrap_or(Duration::from_millis(1000));
+        if probe_budget.is_zero() {
+            base_timeout
+        } else {
+            estimate_timeout(targets[0].1, base_timeout, probe_budget)
         }
+    } else {
+        base_timeout
+    };
+    if args.fast_public && timeout_dur > Duration::from_millis(80) {
+        timeout_dur = Duration::from_millis(80);
     }
 
-    results.sort_by_key(|r| r.port);
-    let mut open: Vec<_> = results.into_iter().filter(|r| r.status == "open").collect();
+    // Wrap semaphore in Arc so it can be cheaply cloned between tasks; it governs
+    // concurrency across the whole host x port Cartesian product.
+    let sem = Arc::new(Semaphore::new(workers));
+    let mut tasks = JoinSet::new();
+
+    for (label, ip, _family) in &targets {
+        for &port in &ports {
+            let sem_clone = Arc::clone(&sem);
+            let ip_cloned = *ip;
+            let label_cloned = label.clone();
+            let timeout_dur = timeout_dur;
+            let use_tls = args.tls || AUTO_TLS_PORTS.contains(&port);
 
-    // Fallback probe for common ports with a longer timeout if nothing found
-    if open.is_empty() {
-        let common = [22u16, 80u16];
-        for &p in &common {
-            let addr = format!("{}:{}", args.host, p);
-            if let Ok(Ok(mut stream)) = timeout(Duration::from_millis(1000), TcpStream::connect(&addr)).await {
-                let mut buf = [0u8; 256];
-                let banner = match timeout(Duration::from_millis(100), stream.read(&mut buf)).await {
-                    Ok(Ok(n)) if n > 0 => Some(String::from_utf8_lossy(&buf[..n]).to_string()),
-                    _ => None,
-                };
-                open.push(ResultRec { port: p, status: "open", banner });
+            tasks.spawn(async move {
+                // Acquire an owned permit; it releases automatically when dropped.
+                let _permit = sem_clone.acquire_owned().await.unwrap();
+                let addr = SocketAddr::new(ip_cloned, port);
+
+                if protocol == "udp" {
+                    let status = probe_udp(addr, timeout_dur).await;
+                    return (label_cloned, ResultRec { port, status, banner: None, tls: None });
+                }
+
+                // attempt connect with timeout + retries on timeout
+                let attempts = retries + 1;
+                let mut status = "filtered";
+                for try_idx in 0..attempts {
+                    match timeout(timeout_dur, TcpStream::connect(addr)).await {
+                        Ok(Ok(mut stream)) => {
+                            if use_tls {
+                                let tls = probe_tls(stream, &label_cloned, timeout_dur).await;
+                                return (label_cloned, ResultRec { port, status: "open", banner: None, tls });
+                            }
+                            let mut buf = [0u8; 256];
+                            let banner = match timeout(Duration::from_millis(50), stream.read(&mut buf)).await {
+                                Ok(Ok(n)) if n > 0 => Some(String::from_utf8_lossy(&buf[..n]).to_string()),
+                                _ => None,
+                            };
+                            return (label_cloned, ResultRec { port, status: "open", banner, tls: None });
+                        }
+                        Ok(Err(e)) => {
+                            status = classify_connect_error(e.kind());
+                            break;
+                        }
+                        Err(_) if try_idx + 1 < attempts => continue, // timeout -> retry
+                        Err(_) => {
+                            status = "filtered"; // no response after all retries -> likely dropped by a firewall
+                            break;
+                        }
+                    }
+                }
+                (label_cloned, ResultRec { port, status, banner: None, tls: None })
+            });
+        }
+    }
+
+    // Drain completed tasks as they finish; if an absolute deadline passes first, abort
+    // whatever's still outstanding and report partial results instead of hanging.
+    let mut by_host: std::collections::HashMap<String, Vec<ResultRec>> = std::collections::HashMap::new();
+    loop {
+        let Some(dl) = deadline else {
+            match tasks.join_next().await {
+                Some(Ok((label, r))) => {
+                    by_host.entry(label).or_default().push(r);
+                }
+                Some(Err(_)) => {}
+                None => break,
             }
+            continue;
+        };
+
+        let remaining = dl.saturating_duration_since(std::time::Instant::now());
+        tokio::select! {
+            res = tasks.join_next() => {
+                match res {
+                    Some(Ok((label, r))) => { by_host.entry(label).or_default().push(r); }
+                    Some(Err(_)) => {}
+                    None => break,
+                }
+            }
+            _ = tokio::time::sleep(remaining) => {
+                tasks.abort_all();
+                break;
+            }
+        }
+    }
+
+    // Any (host, port) pair whose task never completed (aborted past the deadline) is
+    // reported as "unknown" rather than silently dropped.
+    for (label, _ip, _family) in &targets {
+        let completed_ports: std::collections::HashSet<u16> = by_host
+            .get(label)
+            .map(|rs| rs.iter().map(|r| r.port).collect())
+            .unwrap_or_default();
+        let missing: Vec<ResultRec> = missing_ports(&ports, &completed_ports)
+            .into_iter()
+            .map(|port| ResultRec { port, status: "unknown", banner: None, tls: None })
+            .collect();
+        if !missing.is_empty() {
+            by_host.entry(label.clone()).or_default().extend(missing);
         }
     }
 
-    let open_len = open.len();
+    let mut open_count = 0usize;
+    let mut closed_count = 0usize;
+    let mut filtered_count = 0usize;
+    let mut unknown_count = 0usize;
+    let mut grouped: Vec<(String, &'static str, Vec<ResultRec>)> = Vec::with_capacity(targets.len());
+    for (label, ip, family) in &targets {
+        let mut results = by_host.remove(label).unwrap_or_default();
+        results.sort_by_key(|r| r.port);
+        closed_count += results.iter().filter(|r| r.status == "closed").count();
+        filtered_count += results.iter().filter(|r| r.status != "closed" && r.status != "open" && r.status != "unknown").count();
+        unknown_count += results.iter().filter(|r| r.status == "unknown").count();
+        let mut reportable: Vec<_> = results.into_iter().filter(|r| r.status != "closed").collect();
+
+        // Fallback probe for common ports with a longer timeout if nothing found on this host;
+        // skipped once an absolute --max-duration deadline has already elapsed, otherwise it
+        // would tack extra blocking connects onto every "nothing open" host after we just
+        // aborted the rest of the scan to respect that deadline.
+        let deadline_passed = deadline.is_some_and(|dl| std::time::Instant::now() >= dl);
+        if !deadline_passed && reportable.iter().all(|r| r.status != "open") && protocol == "tcp" {
+            for &p in &[22u16, 80u16] {
+                let addr = SocketAddr::new(*ip, p);
+                if let Ok(Ok(mut stream)) = timeout(Duration::from_millis(1000), TcpStream::connect(addr)).await {
+                    let mut buf = [0u8; 256];
+                    let banner = match timeout(Duration::from_millis(100), stream.read(&mut buf)).await {
+                        Ok(Ok(n)) if n > 0 => Some(String::from_utf8_lossy(&buf[..n]).to_string()),
+                        _ => None,
+                    };
+                    reportable.push(ResultRec { port: p, status: "open", banner, tls: None });
+                }
+            }
+        }
+
+        open_count += reportable.iter().filter(|r| r.status == "open").count();
+        grouped.push((label.clone(), family, reportable));
+    }
 
     if args.json {
-        println!("{}", serde_json::to_string_pretty(&open).unwrap());
+        let hosts: Vec<HostResult> = grouped
+            .iter()
+            .map(|(host, family, reportable)| HostResult {
+                host: host.clone(),
+                family: family.to_string(),
+                open_ports: reportable
+                    .iter()
+                    .filter(|r| r.status == "open")
+                    .map(|r| OpenPort { port: r.port, banner: r.banner.clone(), tls: r.tls.clone() })
+                    .collect(),
+                filtered_ports: reportable.iter().filter(|r| r.status == "filtered" || r.status == "open|filtered").map(|r| r.port).collect(),
+                unknown_ports: reportable.iter().filter(|r| r.status == "unknown").map(|r| r.port).collect(),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&hosts).unwrap());
         return;
     }
 
-    if open.is_empty() {
-        println!("No open ports found on {} in range {}-{}", args.host, args.start, args.end);
+    if open_count == 0 && filtered_count == 0 && unknown_count == 0 {
+        println!(
+            "No open ports found across {} host(s) ({} ports scanned each)",
+            targets.len(),
+            ports.len()
+        );
         return;
     }
-    println!("Open ports on {}:", args.host);
-    for r in open {
-        if let Some(b) = r.banner {
-            println!("{} - {} (banner: {:.80})", r.port, r.status, b);
-        } else {
-            println!("{} - {}", r.port, r.status);
+    for (host, family, reportable) in &grouped {
+        if reportable.is_empty() {
+            continue;
+        }
+        println!("Open, filtered and unknown ports on {} ({}):", host, family);
+        for r in reportable {
+            if let Some(t) = &r.tls {
+                println!(
+                    "{} - {} (tls: {}, alpn: {}, subject: {}, issuer: {}, valid: {} - {})",
+                    r.port,
+                    r.status,
+                    t.version,
+                    t.alpn.as_deref().unwrap_or("-"),
+                    t.subject,
+                    t.issuer,
+                    t.not_before,
+                    t.not_after
+                );
+            } else if let Some(b) = &r.banner {
+                println!("{} - {} (banner: {:.80})", r.port, r.status, b);
+            } else {
+                println!("{} - {}", r.port, r.status);
+            }
         }
     }
 
@@ -196,13 +912,216 @@ async fn main() {
     let elapsed = started.elapsed().as_secs_f64();
     let mut elapsed_safe = elapsed;
     if elapsed_safe <= 0.0 { elapsed_safe = 1e-9; }
-    let total_ports = (args.end - args.start + 1) as f64;
+    let total_ports = (ports.len() * targets.len()) as f64;
     let rate = total_ports / elapsed_safe;
     println!(
-        "\nScanned {} ports in {:.2} seconds ({:.1} ports/sec). Open: {}",
-        total_ports as u16,
+        "\nScanned {} ports across {} host(s) in {:.2} seconds ({:.1} ports/sec). Open: {}, Closed: {}, Filtered: {}, Unknown: {}",
+        ports.len(),
+        targets.len(),
         elapsed,
         rate,
-        open_len
+        open_count,
+        closed_count,
+        filtered_count,
+        unknown_count
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ports_single_range_and_service() {
+        assert_eq!(parse_ports("80").unwrap(), vec![80]);
+        assert_eq!(parse_ports("20-22").unwrap(), vec![20, 21, 22]);
+        assert_eq!(parse_ports("https,ssh").unwrap(), vec![22, 443]);
+        assert_eq!(parse_ports("80, 22,80").unwrap(), vec![22, 80]);
+    }
+
+    #[test]
+    fn parse_ports_rejects_reversed_range() {
+        assert!(parse_ports("100-10").is_err());
+    }
+
+    #[test]
+    fn parse_ports_rejects_out_of_range_with_clear_message() {
+        let err = parse_ports("70000").unwrap_err();
+        assert!(err.contains("out of range"), "unexpected message: {}", err);
+    }
+
+    #[test]
+    fn parse_ports_rejects_unknown_service() {
+        assert!(parse_ports("not-a-service").is_err());
+    }
+
+    #[test]
+    fn parse_ports_rejects_empty_spec() {
+        assert!(parse_ports("").is_err());
+    }
+
+    #[test]
+    fn parse_targets_plain_hosts_pass_through() {
+        assert_eq!(parse_targets("example.com").unwrap(), vec!["example.com"]);
+        assert_eq!(parse_targets("10.0.0.1").unwrap(), vec!["10.0.0.1"]);
+    }
+
+    #[test]
+    fn parse_targets_keeps_dotless_hyphenated_hostnames_intact() {
+        assert_eq!(parse_targets("web-01").unwrap(), vec!["web-01"]);
+        assert_eq!(parse_targets("db-1").unwrap(), vec!["db-1"]);
+    }
+
+    #[test]
+    fn parse_targets_expands_dashed_range() {
+        assert_eq!(
+            parse_targets("192.168.1.1-3").unwrap(),
+            vec!["192.168.1.1", "192.168.1.2", "192.168.1.3"]
+        );
+    }
+
+    #[test]
+    fn parse_targets_expands_cidr() {
+        let hosts = parse_targets("10.0.0.0/30").unwrap();
+        assert_eq!(hosts, vec!["10.0.0.1", "10.0.0.2"]);
+    }
+
+    #[test]
+    fn parse_targets_dedupes_across_entries() {
+        assert_eq!(
+            parse_targets("10.0.0.1,10.0.0.1").unwrap(),
+            vec!["10.0.0.1"]
+        );
+    }
+
+    #[test]
+    fn expand_cidr_slash_32_keeps_single_address() {
+        assert_eq!(expand_cidr("10.0.0.5/32").unwrap(), vec!["10.0.0.5"]);
+    }
+
+    #[test]
+    fn expand_cidr_rejects_oversized_block() {
+        let err = expand_cidr("10.0.0.0/16").unwrap_err();
+        assert!(err.contains("exceeds"), "unexpected message: {}", err);
+        assert!(expand_cidr("0.0.0.0/0").is_err());
+    }
+
+    #[test]
+    fn expand_cidr_rejects_bad_prefix() {
+        assert!(expand_cidr("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn expand_dashed_range_basic() {
+        assert_eq!(
+            expand_dashed_range("192.168.1.1-3").unwrap(),
+            vec!["192.168.1.1", "192.168.1.2", "192.168.1.3"]
+        );
+    }
+
+    #[test]
+    fn expand_dashed_range_rejects_reversed_bounds() {
+        assert!(expand_dashed_range("192.168.1.5-1").is_err());
+    }
+
+    #[test]
+    fn interleave_rfc8305_alternates_starting_with_v6() {
+        let v4a: IpAddr = "10.0.0.1".parse().unwrap();
+        let v4b: IpAddr = "10.0.0.2".parse().unwrap();
+        let v6a: IpAddr = "::1".parse().unwrap();
+        let out = interleave_rfc8305(&[v4a, v4b, v6a]);
+        assert_eq!(out, vec![v6a, v4a, v4b]);
+    }
+
+    #[test]
+    fn interleave_rfc8305_handles_single_family() {
+        let v4a: IpAddr = "10.0.0.1".parse().unwrap();
+        let v4b: IpAddr = "10.0.0.2".parse().unwrap();
+        assert_eq!(interleave_rfc8305(&[v4a, v4b]), vec![v4a, v4b]);
+    }
+
+    #[test]
+    fn classify_connect_error_refused_is_closed() {
+        assert_eq!(
+            classify_connect_error(std::io::ErrorKind::ConnectionRefused),
+            "closed"
+        );
+    }
+
+    #[test]
+    fn classify_connect_error_other_kinds_are_filtered() {
+        assert_eq!(classify_connect_error(std::io::ErrorKind::TimedOut), "filtered");
+        assert_eq!(
+            classify_connect_error(std::io::ErrorKind::HostUnreachable),
+            "filtered"
+        );
+    }
+
+    #[test]
+    fn classify_udp_recv_reply_is_open() {
+        assert_eq!(classify_udp_recv(Ok(Ok(4))), "open");
+    }
+
+    #[test]
+    fn classify_udp_recv_refused_is_closed() {
+        assert_eq!(
+            classify_udp_recv(Ok(Err(std::io::ErrorKind::ConnectionRefused))),
+            "closed"
+        );
+    }
+
+    #[test]
+    fn classify_udp_recv_other_error_is_open_filtered() {
+        assert_eq!(
+            classify_udp_recv(Ok(Err(std::io::ErrorKind::PermissionDenied))),
+            "open|filtered"
+        );
+    }
+
+    #[tokio::test]
+    async fn classify_udp_recv_timeout_is_open_filtered() {
+        // `Elapsed` isn't publicly constructible; get a real one from `timeout()`.
+        let elapsed = timeout(Duration::from_millis(0), std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        assert_eq!(classify_udp_recv(Err(elapsed)), "open|filtered");
+    }
+
+    #[test]
+    fn missing_ports_returns_unreported_ports_in_order() {
+        let all = vec![22, 80, 443];
+        let mut completed = std::collections::HashSet::new();
+        completed.insert(80);
+        assert_eq!(missing_ports(&all, &completed), vec![22, 443]);
+    }
+
+    #[test]
+    fn missing_ports_empty_when_all_completed() {
+        let all = vec![22, 80];
+        let completed: std::collections::HashSet<u16> = all.iter().copied().collect();
+        assert!(missing_ports(&all, &completed).is_empty());
+    }
+
+    #[test]
+    fn format_protocol_version_known_version() {
+        assert_eq!(
+            format_protocol_version(Some(tokio_rustls::rustls::ProtocolVersion::TLSv1_3)),
+            "TLSv1_3"
+        );
+    }
+
+    #[test]
+    fn format_protocol_version_none_is_unknown() {
+        assert_eq!(format_protocol_version(None), "unknown");
+    }
+
+    #[test]
+    fn format_alpn_decodes_bytes() {
+        assert_eq!(format_alpn(Some(b"h2")), Some("h2".to_string()));
+    }
+
+    #[test]
+    fn format_alpn_none_when_absent() {
+        assert_eq!(format_alpn(None), None);
+    }
+}